@@ -0,0 +1,31 @@
+use crate::{router::Router, tls::TlsConfig};
+
+/// Server configuration: where to bind, what to serve, and how to behave
+/// under concurrency, keep-alive and (optionally) TLS.
+pub struct Config {
+    pub port: u16,
+    pub directory: String,
+    pub index_file: String,
+    pub not_found_uri: String,
+    pub pool_size: usize,
+    pub keep_alive_timeout_secs: u64,
+    pub max_header_bytes: usize,
+    pub router: Router,
+    pub tls: Option<TlsConfig>,
+}
+
+impl Config {
+    pub fn new(port: u16, directory: String, index_file: String, not_found_uri: String) -> Self {
+        Config {
+            port,
+            directory,
+            index_file,
+            not_found_uri,
+            pool_size: 4,
+            keep_alive_timeout_secs: 30,
+            max_header_bytes: 8 * 1024,
+            router: Router::new(),
+            tls: None,
+        }
+    }
+}
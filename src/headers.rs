@@ -0,0 +1,26 @@
+use std::collections::HashMap;
+
+/// A map of HTTP header names (case-insensitive) to values. Each name holds
+/// a single value; inserting a name again replaces its previous value.
+#[derive(Debug, Clone, Default)]
+pub struct Headers {
+    inner: HashMap<String, String>,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Headers { inner: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, key: &str, value: String) {
+        self.inner.insert(key.to_lowercase(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.inner.get(&key.to_lowercase())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.inner.iter()
+    }
+}
@@ -0,0 +1,12 @@
+pub mod config;
+pub mod headers;
+pub mod request;
+pub mod response;
+pub mod router;
+pub mod server;
+pub mod thread_pool;
+pub mod tls;
+pub mod version;
+
+pub use headers::Headers;
+pub use version::Version;
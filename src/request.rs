@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use crate::Headers;
+
+/// A parsed HTTP request line and headers.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub version: String,
+    pub headers: Headers,
+    pub params: HashMap<String, String>,
+}
+
+impl Request {
+    /// Parses `raw` (everything up to, but not including, the `\r\n\r\n`
+    /// header terminator) into a request line plus headers.
+    pub fn from_string(raw: String) -> Result<Self, String> {
+        let mut lines = raw.split("\r\n");
+
+        let request_line = lines.next().ok_or("missing request line")?;
+        let mut parts = request_line.split_whitespace();
+
+        let method = parts.next().ok_or("missing method")?.to_string();
+        let path = parts.next().ok_or("missing path")?.to_string();
+        let version = parts.next().unwrap_or("HTTP/1.1").to_string();
+
+        let mut headers = Headers::new();
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line.split_once(':').ok_or("malformed header line")?;
+            headers.insert(key.trim(), value.trim().to_string());
+        }
+
+        Ok(Request { method, path, version, headers, params: HashMap::new() })
+    }
+}
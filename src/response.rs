@@ -0,0 +1,34 @@
+use std::io::{self, Write};
+
+use crate::{Headers, Version};
+
+/// An HTTP response, ready to be serialized onto a connection.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status_code: u16,
+    pub status_text: String,
+    pub version: Version,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status_code: u16, status_text: String, version: Version, headers: Headers, body: Vec<u8>) -> Self {
+        Response { status_code, status_text, version, headers, body }
+    }
+
+    /// Writes the status line and headers as text, followed by the raw
+    /// body bytes, so binary bodies survive serialization intact.
+    pub fn write_to(&self, stream: &mut dyn Write) -> io::Result<()> {
+        write!(stream, "{} {} {}\r\n", self.version, self.status_code, self.status_text)?;
+
+        for (key, value) in self.headers.iter() {
+            write!(stream, "{key}: {value}\r\n")?;
+        }
+
+        write!(stream, "\r\n")?;
+        stream.write_all(&self.body)?;
+
+        Ok(())
+    }
+}
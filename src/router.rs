@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::{request::Request, response::Response};
+
+/// A user-supplied endpoint handler, invoked with the matched `Request`
+/// (already carrying any captured path parameters).
+pub type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+enum Segment {
+    Static(String),
+    Param(String),
+}
+
+struct Route {
+    method: String,
+    segments: Vec<Segment>,
+    handler: Handler,
+}
+
+/// Maps `(Method, path-pattern)` pairs to handlers, Express-style, so this
+/// crate can serve dynamic endpoints alongside static files. Patterns
+/// support `:name` segments, whose captured values end up in
+/// `Request::params`.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router { routes: Vec::new() }
+    }
+
+    /// Registers `handler` for `method` requests whose path matches
+    /// `pattern` (e.g. `/users/:id`).
+    pub fn add<F>(&mut self, method: &str, pattern: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        let segments = pattern
+            .trim_matches('/')
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Static(segment.to_string()),
+            })
+            .collect();
+
+        self.routes.push(Route {
+            method: method.to_uppercase(),
+            segments,
+            handler: Box::new(handler),
+        });
+    }
+
+    pub fn get<F>(&mut self, pattern: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.add("GET", pattern, handler);
+    }
+
+    pub fn post<F>(&mut self, pattern: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.add("POST", pattern, handler);
+    }
+
+    /// If a registered route matches `request`'s method and path, captures
+    /// its path parameters onto `request`, invokes the handler, and returns
+    /// its `Response`. Returns `None` when nothing matches, so the caller
+    /// can fall through to static file serving.
+    pub fn try_handle(&self, request: &mut Request) -> Option<Response> {
+        let request_method = request.method.to_uppercase();
+        let request_segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+        for route in &self.routes {
+            if route.method != request_method || route.segments.len() != request_segments.len() {
+                continue;
+            }
+
+            let mut params = HashMap::new();
+            let matches = route.segments.iter().zip(request_segments.iter()).all(
+                |(pattern_segment, actual_segment)| match pattern_segment {
+                    Segment::Static(value) => value == actual_segment,
+                    Segment::Param(name) => {
+                        params.insert(name.clone(), actual_segment.to_string());
+                        true
+                    }
+                },
+            );
+
+            if matches {
+                request.params = params;
+                return Some((route.handler)(request));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Headers, Version};
+
+    fn request(method: &str, path: &str) -> Request {
+        Request::from_string(format!("{method} {path} HTTP/1.1\r\nHost: test\r\n\r\n")).unwrap()
+    }
+
+    fn ok_response() -> Response {
+        Response::new(
+            200,
+            "OK".to_string(),
+            Version::new("HTTP".to_string(), "1.1".to_string()),
+            Headers::new(),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn matches_a_static_route() {
+        let mut router = Router::new();
+        router.get("/health", |_| ok_response());
+
+        let mut req = request("GET", "/health");
+        assert!(router.try_handle(&mut req).is_some());
+    }
+
+    #[test]
+    fn does_not_match_a_different_method() {
+        let mut router = Router::new();
+        router.get("/health", |_| ok_response());
+
+        let mut req = request("POST", "/health");
+        assert!(router.try_handle(&mut req).is_none());
+    }
+
+    #[test]
+    fn does_not_match_a_different_segment_count() {
+        let mut router = Router::new();
+        router.get("/users/:id", |_| ok_response());
+
+        let mut req = request("GET", "/users/42/extra");
+        assert!(router.try_handle(&mut req).is_none());
+    }
+
+    #[test]
+    fn captures_path_parameters() {
+        let mut router = Router::new();
+        router.get("/users/:id", |_| ok_response());
+
+        let mut req = request("GET", "/users/42");
+        assert!(router.try_handle(&mut req).is_some());
+        assert_eq!(req.params.get("id"), Some(&"42".to_string()));
+    }
+
+    #[test]
+    fn falls_through_when_nothing_matches() {
+        let router = Router::new();
+        let mut req = request("GET", "/missing");
+        assert!(router.try_handle(&mut req).is_none());
+    }
+}
@@ -1,165 +1,546 @@
-use std::{error::Error, fs, io::{Read, Write}, net::{TcpListener, TcpStream}};
+use std::{
+    error::Error,
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+    sync::Arc,
+    time::Duration,
+};
 
 use chrono::Utc;
 use log::{info, warn};
+use rustls::{ServerConnection, StreamOwned};
 
-use crate::{config::Config, request::{self, Request}, response::Response, Headers, Version};
+use crate::{config::Config, request::Request, response::Response, thread_pool::ThreadPool, tls, Headers, Version};
+
+/// A request/response stream, plaintext or TLS-wrapped. `handle_connection`
+/// is written against this instead of a concrete `TcpStream` so the same
+/// logic serves both.
+pub trait Connection: Read + Write + Send {}
+impl<T: Read + Write + Send> Connection for T {}
 
 pub fn listen(config: Config) -> Result<(), Box<dyn Error>> {
     // we bind our listener to port from config
     let listener = TcpListener::bind(format!("0.0.0.0:{}", config.port));
-    
+
     if listener.is_err() {
         return Err("this port is already in use.".into());
     }
 
     let listener = listener.unwrap();
+    let pool = ThreadPool::new(config.pool_size);
+    let tls_server_config = config.tls.as_ref().map(tls::build_server_config).transpose()?;
+    let config = Arc::new(config);
 
     info!("Serving directory {} on port {}.", config.directory, config.port);
     loop {
-        // we read every request that the listener has recieved and try to handle it
-        let (stream, _) = listener.accept().unwrap();
+        // `accept()` is all this loop does now; a worker thread from the
+        // pool takes it from here, so one slow or kept-alive client no
+        // longer stalls every other visitor
+        let (tcp_stream, _) = listener.accept().unwrap();
+        let config = Arc::clone(&config);
+        let tls_server_config = tls_server_config.clone();
 
-        let handle = handle_connection(stream, &config);
-        
-        if handle.is_err() {
-            warn!("Error occured while establishing connection with user. {}", handle.err().unwrap());
+        // idle kept-alive connections get dropped after this many seconds
+        // instead of holding a worker thread open forever
+        if let Err(err) = tcp_stream.set_read_timeout(Some(Duration::from_secs(config.keep_alive_timeout_secs))) {
+            warn!("Failed to set read timeout on accepted connection. {}", err);
             continue;
         }
+
+        pool.execute(move || {
+            let stream: Box<dyn Connection> = match tls_server_config {
+                Some(tls_server_config) => match ServerConnection::new(tls_server_config) {
+                    Ok(connection) => Box::new(StreamOwned::new(connection, tcp_stream)),
+                    Err(err) => {
+                        warn!("TLS handshake setup failed. {}", err);
+                        return;
+                    }
+                },
+                // plaintext path stays the default when no TLS config is present
+                None => Box::new(tcp_stream),
+            };
+
+            let handle = handle_connection(stream, &config);
+
+            if handle.is_err() {
+                warn!("Error occured while establishing connection with user. {}", handle.err().unwrap());
+            }
+        });
     }
 }
 
 
-fn handle_connection(mut stream: TcpStream, config: &Config) -> Result<(), Box<dyn Error>> {
-    // we initialize out request buffer that we will be reading request's data into
-    let mut request_buf = [0u8; 4096];
-    // this will represent all out decoded data of request
-    let mut request = String::new();
+fn handle_connection(mut stream: Box<dyn Connection>, config: &Config) -> Result<(), Box<dyn Error>> {
+    // bytes read past the end of one request (a pipelined next request line,
+    // or a body chunk that overran into it) are carried forward here instead
+    // of being dropped, so the next iteration picks up where this one left off
+    let mut leftover = Vec::new();
 
-    // as because we cannot simply read the entire request from the network i/o
-    // we read the bytes of it in chunks, sequentially
+    // HTTP/1.1 connections are persistent by default, so we keep serving
+    // requests off the same stream until the client (or we) ask to close it
     loop {
-        let bytes_read = stream.read(&mut request_buf).unwrap();
-        if bytes_read == 0 {
-            // if we had read 0 bytes it means that we read entirity of the request
-            // so we stop reading it
-            break;
+        let (head, trailing) = match read_request_head(&mut stream, config.max_header_bytes, std::mem::take(&mut leftover))? {
+            RequestHead::Closed => return Ok(()),
+            RequestHead::TooLarge => {
+                write_error_response(&mut stream, 431, "Request Header Fields Too Large")?;
+                return Ok(());
+            }
+            RequestHead::Parsed { head, trailing } => (head, trailing),
+        };
+
+        // we parse our request
+        let request = Request::from_string(String::from_utf8_lossy(&head).to_string());
+
+        let mut request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                warn!("Error while parsing request. Invalid request. {}", err);
+                write_error_response(&mut stream, 400, "Bad Request")?;
+                return Ok(());
+            }
+        };
+
+        // before we can read the next request off this stream we must consume
+        // whatever body this one carried; anything read past that body is the
+        // start of the next request and is kept in `leftover` for next time
+        leftover = consume_request_body(&mut stream, &request, trailing)?;
+
+        info!(
+            "Requested path {}.",
+            request.path
+        );
+
+        let keep_alive = wants_keep_alive(&request);
+
+        // dynamic routes take priority over static files, so users can mix
+        // JSON endpoints, redirects and health checks in with served assets
+        if let Some(mut response) = config.router.try_handle(&mut request) {
+            response.headers.insert(
+                "Connection",
+                (if keep_alive { "keep-alive" } else { "close" }).to_string()
+            );
+
+            info!("Responding with status {} from router.", response.status_code);
+
+            response.write_to(&mut stream)?;
+            stream.flush()?;
+
+            if !keep_alive {
+                return Ok(());
+            }
+
+            continue;
         }
 
-        // we decode the request chunk we read from network i/o
-        // and append it to out request string
-        let request_chunk = String::from_utf8_lossy(&request_buf[0..bytes_read]).to_string();
-        request.push_str(request_chunk.as_str());
+        if request.path.ends_with("/") {
+            request.path = format!("{}{}", request.path, config.index_file).to_string();
+        }
 
+        let resource_path = format!(
+            "{}/{}",
+            config.directory.trim_end_matches("/"),
+            request.path
+        );
+        let resource_content = fs::read(resource_path);
 
-        // the full request has been recieved
-        if request_chunk.ends_with("\r\n\r\n") {
-            break;
+        let response = if let Ok(resource_content) = resource_content {
+            let resource_len = resource_content.len();
+            let content_type = mime_guess::from_path(&request.path)
+                .first()
+                .map(|mime| mime.to_string())
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            let byte_range = request
+                .headers
+                .get("Range")
+                .and_then(|value| parse_byte_range(value, resource_len));
+
+            if let Some(ByteRange::Unsatisfiable) = byte_range {
+                let mut headers = Headers::new();
+
+                headers.insert(
+                    "Content-Lenght",
+                    "0".to_string()
+                );
+                headers.insert(
+                    "Content-Range",
+                    format!("bytes */{}", resource_len)
+                );
+                headers.insert(
+                    "Server",
+                    "Quickserving".to_string()
+                );
+                headers.insert(
+                    "Connection",
+                    (if keep_alive { "keep-alive" } else { "close" }).to_string()
+                );
+
+                Response::new(
+                    416,
+                    "Range Not Satisfiable".to_string(),
+                    Version::new(
+                        "HTTP".to_string(),
+                        "1.1".to_string()
+                    ),
+                    headers,
+                    Vec::new()
+                )
+            } else if let Some(ByteRange::Satisfiable { start, end }) = byte_range {
+                let sliced_content = resource_content[start..=end].to_vec();
+                let sliced_len = end - start + 1;
+
+                let mut headers = Headers::new();
+
+                headers.insert(
+                    "Content-Type",
+                    content_type
+                );
+                headers.insert(
+                    "Content-Lenght",
+                    sliced_len.to_string()
+                );
+                headers.insert(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, end, resource_len)
+                );
+                headers.insert(
+                    "Accept-Ranges",
+                    "bytes".to_string()
+                );
+                headers.insert(
+                    "Server",
+                    "Quickserving".to_string()
+                );
+                headers.insert(
+                    "Date",
+                    Utc::now().to_string()
+                );
+                headers.insert(
+                    "Connection",
+                    (if keep_alive { "keep-alive" } else { "close" }).to_string()
+                );
+
+                Response::new(
+                    206,
+                    "Partial Content".to_string(),
+                    Version::new(
+                        "HTTP".to_string(),
+                        "1.1".to_string()
+                    ),
+                    headers,
+                    sliced_content
+                )
+            } else {
+                let mut headers = Headers::new();
+
+                headers.insert(
+                    "Content-Type",
+                    content_type
+                );
+                headers.insert(
+                    "Content-Lenght",
+                    resource_len.to_string()
+                );
+                headers.insert(
+                    "Accept-Ranges",
+                    "bytes".to_string()
+                );
+                headers.insert(
+                    "Server",
+                    "Quickserving".to_string()
+                );
+                headers.insert(
+                    "Date",
+                    Utc::now().to_string()
+                );
+                headers.insert(
+                    "Connection",
+                    (if keep_alive { "keep-alive" } else { "close" }).to_string()
+                );
+
+
+                Response::new(
+                    200,
+                    "OK".to_string(),
+                    Version::new(
+                        "HTTP".to_string(),
+                        "1.1".to_string()
+                    ),
+                    headers,
+                    resource_content
+                )
+            }
+        } else {
+            let resource_content = fs::read(format!(
+                "{}/{}",
+                &config.directory.trim_end_matches('/'),
+                &config.not_found_uri
+            )).unwrap_or_else(|_| b"404".to_vec());
+            let resource_len = resource_content.len();
+
+            let mut headers = Headers::new();
+
+            headers.insert(
+                "Content-Type",
+                "text/html".to_string()
+            );
+            headers.insert(
+                "Content-Lenght",
+                resource_len.to_string()
+            );
+            headers.insert(
+                "Server",
+                "Quickserving".to_string()
+            );
+            headers.insert(
+                "Connection",
+                (if keep_alive { "keep-alive" } else { "close" }).to_string()
+            );
+
+
+            Response::new(
+                404,
+                "Resource not found".to_string(),
+                Version::new(
+                    "HTTP".to_string(),
+                    "1.1".to_string()
+                ),
+                headers,
+                resource_content
+            )
+        };
+
+        info!("Responding with status {}.", response.status_code);
+
+        response.write_to(&mut stream)?;
+        stream.flush()?;
+
+        if !keep_alive {
+            return Ok(());
         }
-    };
+    }
+}
+
+/// The result of trying to read one request's header block off a stream.
+enum RequestHead {
+    /// The peer closed its side with nothing left to read.
+    Closed,
+    /// The header block grew past `max_header_bytes` before a terminator
+    /// was found.
+    TooLarge,
+    /// `head` is the header block (without the trailing `\r\n\r\n`);
+    /// `trailing` is whatever body bytes were already read past it.
+    Parsed { head: Vec<u8>, trailing: Vec<u8> },
+}
+
+/// Reads a single request's header block off `stream`, accumulating into a
+/// growing buffer and scanning the *entire* accumulated buffer for
+/// `\r\n\r\n` rather than just the latest chunk, so a terminator that
+/// straddles two reads is never missed. `prefix` seeds the buffer with any
+/// bytes already read past the previous request (e.g. from pipelining), so
+/// they're scanned before we block on the socket for more.
+fn read_request_head(stream: &mut dyn Connection, max_header_bytes: usize, prefix: Vec<u8>) -> Result<RequestHead, Box<dyn Error>> {
+    let mut buf = prefix;
+    let mut chunk = [0u8; 4096];
 
-    // we parse our request
-    let request = Request::from_string(request);
+    loop {
+        if let Some(pos) = find_header_terminator(&buf) {
+            let trailing = buf.split_off(pos + 4);
+            buf.truncate(pos);
+            return Ok(RequestHead::Parsed { head: buf, trailing });
+        }
+
+        if buf.len() > max_header_bytes {
+            return Ok(RequestHead::TooLarge);
+        }
 
-    if request.is_err() {
-        warn!("Error while parsing request. Invalid request. {}", request.err().unwrap());
-        return Err("Error while parsing request. Invalid request.".into());
+        let bytes_read = stream.read(&mut chunk)?;
+        if bytes_read == 0 {
+            return Ok(RequestHead::Closed);
+        }
+
+        buf.extend_from_slice(&chunk[0..bytes_read]);
     }
+}
+
+/// Finds the byte offset of the `\r\n\r\n` header/body boundary, if any.
+fn find_header_terminator(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|window| window == b"\r\n\r\n")
+}
 
-    let mut request = request.unwrap();
+/// Consumes the request body declared by `Content-Length`, if any, so a
+/// pipelined or kept-alive stream doesn't desync on the next read.
+/// `trailing` is whatever bytes were already pulled in past the header
+/// terminator while looking for it; it may contain part (or all) of the
+/// body, and possibly bytes belonging to the *next* request. Returns
+/// whatever bytes are left over past this request's body, to be fed back
+/// into the next `read_request_head` call instead of discarded.
+fn consume_request_body(stream: &mut dyn Connection, request: &Request, trailing: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let content_length = request
+        .headers
+        .get("Content-Length")
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
 
-    if request.path.ends_with("/") {
-        request.path = format!("{}{}", request.path, config.index_file).to_string();
+    if trailing.len() >= content_length {
+        return Ok(trailing[content_length..].to_vec());
     }
-    
-    info!(
-        "Requested path {}.",
-        request.path
-    );
 
-    let resource_path = format!(
-        "{}/{}",
-        config.directory.trim_end_matches("/"),
-        request.path
+    let mut remaining = content_length - trailing.len();
+    let mut discard_buf = [0u8; 4096];
+
+    while remaining > 0 {
+        let to_read = remaining.min(discard_buf.len());
+        let bytes_read = stream.read(&mut discard_buf[0..to_read])?;
+        if bytes_read == 0 {
+            break;
+        }
+        remaining -= bytes_read;
+    }
+
+    Ok(Vec::new())
+}
+
+/// Builds and sends a minimal error response, used for the connection-level
+/// failures (oversized headers, malformed request lines) that happen before
+/// we have a usable `Request` to build a normal response from.
+fn write_error_response(stream: &mut dyn Connection, status_code: u16, status_text: &str) -> Result<(), Box<dyn Error>> {
+    let body = status_text.as_bytes().to_vec();
+
+    let mut headers = Headers::new();
+    headers.insert("Content-Type", "text/plain".to_string());
+    headers.insert("Content-Lenght", body.len().to_string());
+    headers.insert("Server", "Quickserving".to_string());
+    headers.insert("Connection", "close".to_string());
+
+    let response = Response::new(
+        status_code,
+        status_text.to_string(),
+        Version::new("HTTP".to_string(), "1.1".to_string()),
+        headers,
+        body,
     );
-    let resource_content = fs::read_to_string(resource_path);
-
-    let response = if resource_content.is_err() {
-        let resource_content = fs::read_to_string(format!(
-            "{}/{}", 
-            &config.directory.trim_end_matches('/'),
-            &config.not_found_uri
-        )).unwrap_or("404".to_string());
-        let resource_len = resource_content.len();
-        
-        let mut headers = Headers::new();
-
-        headers.insert(
-            &"Content-Type".to_string(), 
-            "text/html".to_string()
-        );
-        headers.insert(
-            &"Content-Lenght".to_string(), 
-            resource_len.to_string()
-        );
-        headers.insert(
-            &"Server".to_string(), 
-            "Quickserving".to_string()
-        );
 
-    
-        Response::new(
-            404, 
-            "Resource not found".to_string(),
-            Version::new(
-                "HTTP".to_string(),
-                "1.1".to_string()
-            ),
-            headers,
-            resource_content
-        )
+    response.write_to(stream)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// The outcome of matching a `Range` header against a resource's length.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Satisfiable { start: usize, end: usize },
+    Unsatisfiable,
+}
+
+/// Parses a `Range: bytes=<start>-<end>` header value against `total_len`,
+/// accepting the explicit (`500-999`), open-ended (`500-`) and suffix
+/// (`-500`) forms. Returns `None` when the header isn't a byte-range we
+/// understand, in which case the caller should fall back to a full 200.
+fn parse_byte_range(value: &str, total_len: usize) -> Option<ByteRange> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    let (start_str, end_str) = spec.split_once('-')?;
+
+    let (start, end) = if start_str.is_empty() {
+        // suffix range: the last `end_str` bytes of the resource
+        let suffix_len: usize = end_str.parse().ok()?;
+        if suffix_len == 0 || total_len == 0 {
+            return Some(ByteRange::Unsatisfiable);
+        }
+        (total_len.saturating_sub(suffix_len), total_len - 1)
     } else {
-        let resource_content = resource_content.unwrap();
-        let resource_len = resource_content.len();
+        let start: usize = start_str.parse().ok()?;
+        let end: usize = if end_str.is_empty() {
+            total_len.saturating_sub(1)
+        } else {
+            end_str.parse().ok()?
+        };
+        (start, end)
+    };
 
-        let mut headers = Headers::new();
+    if start >= total_len || start > end {
+        return Some(ByteRange::Unsatisfiable);
+    }
 
-        headers.insert(
-            &"Content-Type".to_string(), 
-            mime_guess::from_path(request.path).first().unwrap().to_string()
-        );
-        headers.insert(
-            &"Content-Lenght".to_string(), 
-            resource_len.to_string()
+    Some(ByteRange::Satisfiable { start, end: end.min(total_len - 1) })
+}
+
+/// HTTP/1.1 defaults to persistent connections unless the client asks to
+/// close; HTTP/1.0 is the opposite and only stays open on an explicit
+/// `Connection: keep-alive`.
+fn wants_keep_alive(request: &Request) -> bool {
+    let connection_header = request
+        .headers
+        .get("Connection")
+        .map(|value| value.to_lowercase());
+
+    match connection_header.as_deref() {
+        Some("close") => false,
+        Some("keep-alive") => true,
+        _ => request.version.contains("1.1"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_an_explicit_range() {
+        assert_eq!(
+            parse_byte_range("bytes=500-999", 1000),
+            Some(ByteRange::Satisfiable { start: 500, end: 999 })
         );
-        headers.insert(
-            &"Server".to_string(), 
-            "Quickserving".to_string()
+    }
+
+    #[test]
+    fn parses_an_open_ended_range() {
+        assert_eq!(
+            parse_byte_range("bytes=500-", 1000),
+            Some(ByteRange::Satisfiable { start: 500, end: 999 })
         );
-        headers.insert(
-            &"Date".to_string(), 
-            Utc::now().to_string()
+    }
+
+    #[test]
+    fn parses_a_suffix_range() {
+        assert_eq!(
+            parse_byte_range("bytes=-500", 1000),
+            Some(ByteRange::Satisfiable { start: 500, end: 999 })
         );
+    }
 
-    
-        Response::new(
-            200, 
-            "OK".to_string(),
-            Version::new(
-                "HTTP".to_string(),
-                "1.1".to_string()
-            ),
-            headers,
-            resource_content
-        )
-    };
+    #[test]
+    fn clamps_a_suffix_range_longer_than_the_resource() {
+        assert_eq!(
+            parse_byte_range("bytes=-5000", 1000),
+            Some(ByteRange::Satisfiable { start: 0, end: 999 })
+        );
+    }
 
-    let response_string = response.to_string();
+    #[test]
+    fn rejects_a_start_past_the_end_of_the_resource() {
+        assert_eq!(
+            parse_byte_range("bytes=1000-1999", 1000),
+            Some(ByteRange::Unsatisfiable)
+        );
+    }
 
-    info!("Responding with: {}", response_string);
+    #[test]
+    fn rejects_a_start_after_the_end() {
+        assert_eq!(
+            parse_byte_range("bytes=500-100", 1000),
+            Some(ByteRange::Unsatisfiable)
+        );
+    }
 
-    stream.write_all(response_string.as_bytes()).unwrap();
-    stream.flush().unwrap();
+    #[test]
+    fn ignores_a_header_without_the_bytes_prefix() {
+        assert_eq!(parse_byte_range("items=500-999", 1000), None);
+    }
 
-    return Ok(());
+    #[test]
+    fn ignores_an_unparseable_range() {
+        assert_eq!(parse_byte_range("bytes=abc-999", 1000), None);
+    }
 }
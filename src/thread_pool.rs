@@ -0,0 +1,90 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use log::warn;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed-size pool of worker threads that pull jobs off a shared queue,
+/// so accepting a connection and serving it happen on different threads.
+pub struct ThreadPool {
+    workers: Vec<Worker>,
+    sender: Option<mpsc::Sender<Job>>,
+}
+
+impl ThreadPool {
+    /// Creates a pool with `size` worker threads. Panics if `size` is 0,
+    /// since a pool with no workers could never make progress.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0);
+
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let mut workers = Vec::with_capacity(size);
+        for id in 0..size {
+            workers.push(Worker::new(id, Arc::clone(&receiver)));
+        }
+
+        ThreadPool { workers, sender: Some(sender) }
+    }
+
+    /// Hands `job` to whichever worker becomes free next.
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.sender.as_ref().unwrap().send(Box::new(job)).unwrap();
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // dropping the sender first unblocks the workers' recv() calls so
+        // they can see the channel has closed and exit their loop
+        drop(self.sender.take());
+
+        for worker in &mut self.workers {
+            if let Some(handle) = worker.handle.take() {
+                if handle.join().is_err() {
+                    warn!("Worker {} panicked while shutting down.", worker.id);
+                }
+            }
+        }
+    }
+}
+
+struct Worker {
+    id: usize,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Worker {
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Self {
+        let handle = thread::Builder::new()
+            .name(format!("quickserving-worker-{id}"))
+            .spawn(move || loop {
+                let job = receiver.lock().unwrap().recv();
+
+                match job {
+                    // a panicking job is caught and logged here rather than
+                    // left to unwind the worker thread; otherwise a single
+                    // bad job (e.g. a handler that unwraps on bad input)
+                    // would permanently shrink the pool by one
+                    Ok(job) => {
+                        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            warn!("Worker {id} panicked while running a job; worker stays alive.");
+                        }
+                    }
+                    // sender was dropped, the pool is shutting down
+                    Err(_) => break,
+                }
+            })
+            .unwrap();
+
+        Worker { id, handle: Some(handle) }
+    }
+}
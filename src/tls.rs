@@ -0,0 +1,47 @@
+use std::{error::Error, fs::File, io::BufReader, sync::Arc};
+
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    ServerConfig,
+};
+
+/// Certificate chain + private key paths for serving HTTPS directly,
+/// without a reverse proxy in front.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_chain_path: String,
+    pub private_key_path: String,
+}
+
+impl TlsConfig {
+    pub fn new(cert_chain_path: String, private_key_path: String) -> Self {
+        TlsConfig { cert_chain_path, private_key_path }
+    }
+}
+
+/// Builds the `rustls` server config used to wrap each accepted connection
+/// in a TLS session, loading the certificate chain and private key once at
+/// startup rather than per connection.
+pub fn build_server_config(tls: &TlsConfig) -> Result<Arc<ServerConfig>, Box<dyn Error>> {
+    let certs = load_cert_chain(&tls.cert_chain_path)?;
+    let key = load_private_key(&tls.private_key_path)?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(Arc::new(config))
+}
+
+fn load_cert_chain(path: &str) -> Result<Vec<CertificateDer<'static>>, Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(certs)
+}
+
+fn load_private_key(path: &str) -> Result<PrivateKeyDer<'static>, Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    rustls_pemfile::private_key(&mut reader)?.ok_or_else(|| "no private key found in file".into())
+}
@@ -0,0 +1,20 @@
+use std::fmt;
+
+/// An HTTP version, e.g. `HTTP/1.1`.
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub name: String,
+    pub version: String,
+}
+
+impl Version {
+    pub fn new(name: String, version: String) -> Self {
+        Version { name, version }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.name, self.version)
+    }
+}